@@ -0,0 +1,163 @@
+//! Compiler-quality parse diagnostics, rendered from a `Reader`'s source text.
+
+const TAB_STOP: usize = 8;
+
+/// A half-open byte range into the source text passed to `Reader::new`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start_offset: usize,
+    pub end_offset: usize,
+}
+
+/// Display width of a single character: tabs are handled by the caller
+/// (they depend on the current column), and East Asian wide characters
+/// count as two columns so carets line up under the real glyphs.
+///
+/// This only covers the common CJK/Hangul/fullwidth blocks rather than
+/// the full Unicode East Asian Width table, which is enough for SVG
+/// documents carrying CJK text content or attribute values.
+fn char_width(c: char) -> usize {
+    let c = c as u32;
+    let wide = (c >= 0x1100 && c <= 0x115F) ||
+        (c >= 0x2E80 && c <= 0x303E) ||
+        (c >= 0x3041 && c <= 0x33FF) ||
+        (c >= 0x3400 && c <= 0x4DBF) ||
+        (c >= 0x4E00 && c <= 0x9FFF) ||
+        (c >= 0xA000 && c <= 0xA4CF) ||
+        (c >= 0xAC00 && c <= 0xD7A3) ||
+        (c >= 0xF900 && c <= 0xFAFF) ||
+        (c >= 0xFF00 && c <= 0xFF60) ||
+        (c >= 0xFFE0 && c <= 0xFFE6) ||
+        (c >= 0x20000 && c <= 0x3FFFD);
+
+    if wide { 2 } else { 1 }
+}
+
+/// Advances a display column from `start` across `text`, expanding tabs
+/// to the next multiple of `TAB_STOP`.
+fn advance_column(text: &str, start: usize) -> usize {
+    let mut column = start;
+
+    for c in text.chars() {
+        column += match c {
+            '\t' => TAB_STOP - (column % TAB_STOP),
+            _ => char_width(c),
+        };
+    }
+
+    column
+}
+
+/// Renders `line` with tabs expanded to spaces, so the printed source
+/// line matches the column math used to place the underline.
+fn expand_tabs(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut column = 0;
+
+    for c in line.chars() {
+        match c {
+            '\t' => {
+                let width = TAB_STOP - (column % TAB_STOP);
+                for _ in 0..width {
+                    out.push(' ');
+                }
+                column += width;
+            },
+            _ => {
+                out.push(c);
+                column += char_width(c);
+            },
+        }
+    }
+
+    out
+}
+
+/// Renders a multi-line snippet pinpointing `span` within `text`, in the
+/// style of `annotate-snippets`: a gutter with the 1-based line number,
+/// the offending source line, and a line of carets underlining the span.
+///
+/// A zero-width span still renders a single caret, and a span crossing
+/// a newline is underlined only to the end of its first line.
+pub fn render_snippet(text: &str, span: Span, message: &str) -> String {
+    let before = &text[..span.start_offset];
+    let line_number = before.chars().filter(|&c| c == '\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = text[span.start_offset..]
+        .find('\n')
+        .map(|i| span.start_offset + i)
+        .unwrap_or_else(|| text.len());
+
+    let line = &text[line_start..line_end];
+    let underline_end = if span.end_offset > line_end { line_end } else { span.end_offset };
+
+    let start_column = advance_column(&text[line_start..span.start_offset], 0);
+    let mut caret_len = advance_column(&text[span.start_offset..underline_end], start_column) - start_column;
+    if caret_len == 0 {
+        caret_len = 1;
+    }
+
+    let gutter = line_number.to_string();
+    let pad: String = " ".repeat(gutter.len());
+
+    format!(
+        "{gutter} | {line}\n{pad} | {spaces}{carets} {message}",
+        gutter = gutter,
+        line = expand_tabs(line),
+        pad = pad,
+        spaces = " ".repeat(start_column),
+        carets = "^".repeat(caret_len),
+        message = message,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_snippet, Span};
+    use reader::Reader;
+
+    #[test]
+    fn renders_a_span_derived_from_a_reader_over_non_ascii_source() {
+        let text = "<svg>中中 width=\"10\">";
+        let mut reader = Reader::new(text);
+
+        reader.consume_while(|c| c != 'w');
+        let span = reader.capture_span(|r| r.consume_until_char('=')).unwrap();
+
+        let rendered = render_snippet(text, span, "unknown attribute `width`");
+
+        assert_eq!(rendered, "1 | <svg>中中 width=\"10\">\n  |           ^^^^^ unknown attribute `width`");
+    }
+
+    #[test]
+    fn underlines_a_span() {
+        let text = "<svg wdith=\"10\">";
+        let rendered = render_snippet(text, Span { start_offset: 5, end_offset: 10 }, "unknown attribute `wdith`");
+
+        assert_eq!(rendered, "1 | <svg wdith=\"10\">\n  |      ^^^^^ unknown attribute `wdith`");
+    }
+
+    #[test]
+    fn zero_width_span_at_eof_still_renders_a_caret() {
+        let text = "<svg>";
+        let rendered = render_snippet(text, Span { start_offset: 5, end_offset: 5 }, "unexpected end of input");
+
+        assert_eq!(rendered, "1 | <svg>\n  |      ^ unexpected end of input");
+    }
+
+    #[test]
+    fn span_crossing_a_newline_underlines_to_end_of_first_line() {
+        let text = "<svg\n  width=\"10\">";
+        let rendered = render_snippet(text, Span { start_offset: 2, end_offset: 12 }, "unterminated tag");
+
+        assert_eq!(rendered, "1 | <svg\n  |   ^^ unterminated tag");
+    }
+
+    #[test]
+    fn aligns_by_display_width_not_char_count() {
+        let text = "<svg>\t\u{4e2d}\u{4e2d}</svg>";
+        let rendered = render_snippet(text, Span { start_offset: 9, end_offset: 12 }, "boom");
+
+        assert_eq!(rendered, "1 | <svg>   中中</svg>\n  |           ^^ boom");
+    }
+}
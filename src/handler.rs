@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use parser::{Attribute, Event, Parser};
+use reader::QName;
+
+/// Visitor-style callbacks invoked while `Render` drives a `Parser`,
+/// following orgize's `HtmlHandler`/`Render` design. Each callback gets
+/// the output writer directly, so a handler can serialize, transform,
+/// or simply observe events as they arrive. All methods default to a
+/// no-op so a handler only needs to override what it cares about.
+pub trait Handler {
+    fn start_element<W: Write>(&mut self, _writer: &mut W, _name: QName, _attributes: &[Attribute]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn end_element<W: Write>(&mut self, _writer: &mut W, _name: QName) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn text<W: Write>(&mut self, _writer: &mut W, _text: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn comment<W: Write>(&mut self, _writer: &mut W, _text: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn cdata<W: Write>(&mut self, _writer: &mut W, _text: &str) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Drives a `Parser` over `'s`, feeding each event to a `Handler` that
+/// writes to `W`. Generic over both, so a caller can plug in their own
+/// handler (a minifier, an attribute rewriter, ...) without touching
+/// the parser itself.
+pub struct Render<'s, H, W> where H: Handler, W: Write {
+    parser: Parser<'s>,
+    handler: H,
+    writer: W,
+}
+
+impl<'s, H, W> Render<'s, H, W> where H: Handler, W: Write {
+    #[inline]
+    pub fn new(text: &'s str, handler: H, writer: W) -> Render<'s, H, W> {
+        Render {
+            parser: Parser::new(text),
+            handler: handler,
+            writer: writer,
+        }
+    }
+
+    /// Pulls events from the parser until it's exhausted, feeding each
+    /// element, text, comment and CDATA event to the handler; `<?...?>`
+    /// declarations, `<!DOCTYPE ...>` declarations and unmatched end
+    /// tags are skipped. Returns the handler and writer back to the
+    /// caller, e.g. to inspect a `StatsHandler`'s counts or an in-memory
+    /// writer's buffer.
+    pub fn run(mut self) -> io::Result<(H, W)> {
+        while let Some(event) = self.parser.next() {
+            match event {
+                Event::Open { name, attributes } => self.handler.start_element(&mut self.writer, name, &attributes)?,
+                Event::Close { name } => self.handler.end_element(&mut self.writer, name)?,
+                Event::Text(text) => self.handler.text(&mut self.writer, text)?,
+                Event::Comment(text) => self.handler.comment(&mut self.writer, text)?,
+                Event::CData(text) => self.handler.cdata(&mut self.writer, text)?,
+                Event::Declaration | Event::Doctype(_) | Event::UnmatchedClose { .. } => {},
+            }
+        }
+
+        Ok((self.handler, self.writer))
+    }
+}
+
+/// Writes `value` as it would appear inside a double-quoted attribute,
+/// escaping the characters that would otherwise end the attribute early
+/// or be misread by another parser: `&`, `"`, `<`, and `>`. The source
+/// may have used single quotes (so `"` was never escaped on the way in),
+/// so this can't just copy the value through verbatim.
+fn write_escaped_attribute_value<W: Write>(writer: &mut W, value: &str) -> io::Result<()> {
+    for c in value.chars() {
+        match c {
+            '&' => write!(writer, "&amp;")?,
+            '"' => write!(writer, "&quot;")?,
+            '<' => write!(writer, "&lt;")?,
+            '>' => write!(writer, "&gt;")?,
+            _ => write!(writer, "{}", c)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Re-serializes SVG as it was parsed, useful for round-tripping and
+/// whitespace normalization. Self-closing elements are written as an
+/// explicit start/end pair, and the events `Render` skips (declarations,
+/// doctypes, unmatched end tags) do not appear in the output.
+pub struct EchoHandler;
+
+impl Handler for EchoHandler {
+    fn start_element<W: Write>(&mut self, writer: &mut W, name: QName, attributes: &[Attribute]) -> io::Result<()> {
+        write!(writer, "<{}", name)?;
+
+        for attribute in attributes {
+            write!(writer, " {}=\"", attribute.name)?;
+            write_escaped_attribute_value(writer, attribute.value)?;
+            write!(writer, "\"")?;
+        }
+
+        write!(writer, ">")
+    }
+
+    fn end_element<W: Write>(&mut self, writer: &mut W, name: QName) -> io::Result<()> {
+        write!(writer, "</{}>", name)
+    }
+
+    fn text<W: Write>(&mut self, writer: &mut W, text: &str) -> io::Result<()> {
+        write!(writer, "{}", text)
+    }
+
+    fn comment<W: Write>(&mut self, writer: &mut W, text: &str) -> io::Result<()> {
+        write!(writer, "<!--{}-->", text)
+    }
+
+    fn cdata<W: Write>(&mut self, writer: &mut W, text: &str) -> io::Result<()> {
+        write!(writer, "<![CDATA[{}]]>", text)
+    }
+}
+
+/// Counts elements by tag name, ignoring everything else.
+pub struct StatsHandler {
+    pub counts: HashMap<String, usize>,
+}
+
+impl StatsHandler {
+    #[inline]
+    pub fn new() -> StatsHandler {
+        StatsHandler { counts: HashMap::new() }
+    }
+}
+
+impl Handler for StatsHandler {
+    fn start_element<W: Write>(&mut self, _writer: &mut W, name: QName, _attributes: &[Attribute]) -> io::Result<()> {
+        *self.counts.entry(name.to_string()).or_insert(0) += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EchoHandler, Render, StatsHandler};
+
+    #[test]
+    fn echo_handler_round_trips() {
+        let (_, writer) = Render::new("<svg><rect width=\"10\"/>text</svg>", EchoHandler, Vec::new())
+            .run()
+            .unwrap();
+
+        assert_eq!(String::from_utf8(writer).unwrap(), "<svg><rect width=\"10\"></rect>text</svg>");
+    }
+
+    #[test]
+    fn echo_handler_escapes_quotes_in_attribute_values() {
+        let (_, writer) = Render::new("<rect title='1\"0'/>", EchoHandler, Vec::new())
+            .run()
+            .unwrap();
+
+        assert_eq!(String::from_utf8(writer).unwrap(), "<rect title=\"1&quot;0\"></rect>");
+    }
+
+    #[test]
+    fn echo_handler_round_trips_cdata() {
+        let (_, writer) = Render::new("<svg><![CDATA[<raw>&amp;]]></svg>", EchoHandler, Vec::new())
+            .run()
+            .unwrap();
+
+        assert_eq!(String::from_utf8(writer).unwrap(), "<svg><![CDATA[<raw>&amp;]]></svg>");
+    }
+
+    #[test]
+    fn stats_handler_counts_elements_by_tag() {
+        let (handler, _) = Render::new("<svg><rect/><rect/><circle/></svg>", StatsHandler::new(), Vec::new())
+            .run()
+            .unwrap();
+
+        assert_eq!(handler.counts.get("rect"), Some(&2));
+        assert_eq!(handler.counts.get("circle"), Some(&1));
+        assert_eq!(handler.counts.get("svg"), Some(&1));
+    }
+}
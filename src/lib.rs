@@ -0,0 +1,9 @@
+pub mod diagnostics;
+pub mod reader;
+pub mod parser;
+pub mod handler;
+
+pub use diagnostics::Span;
+pub use reader::{Mark, QName, Reader};
+pub use parser::{Attribute, Event, Parser};
+pub use handler::{EchoHandler, Handler, Render, StatsHandler};
@@ -0,0 +1,346 @@
+use std::cell::Cell;
+
+use reader::{QName, Reader};
+
+/// An attribute on an `Event::Open` element, e.g. `width="100"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attribute<'s> {
+    pub name: QName<'s>,
+    pub value: &'s str,
+}
+
+/// A single construct encountered while pulling through the document.
+///
+/// Borrows directly from the source text handed to `Parser::new`, so
+/// producing events is zero-copy.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<'s> {
+    Open { name: QName<'s>, attributes: Vec<Attribute<'s>> },
+    Close { name: QName<'s> },
+    /// A closing tag that doesn't match the innermost open element, e.g.
+    /// `</svg>` while `<rect>` is still open. `expected` is the name on
+    /// top of the openers stack (`None` if nothing was open at all).
+    UnmatchedClose { expected: Option<QName<'s>>, found: QName<'s> },
+    Text(&'s str),
+    Comment(&'s str),
+    CData(&'s str),
+    Declaration,
+    /// An `<!...>` markup declaration that isn't a comment or `CDATA`
+    /// section, e.g. `<!DOCTYPE svg>`. Holds the raw content between
+    /// `<!` and the closing `>`.
+    Doctype(&'s str),
+}
+
+/// Event-based pull parser layered on top of `Reader`.
+///
+/// `Parser` drives a `Reader` forward one construct at a time and yields
+/// `Event`s, mirroring the pull-parser style used by crates like jotdown
+/// and orgize. A self-closing element such as `<rect .../>` is reported
+/// as an `Open` immediately followed by a synthetic `Close`.
+pub struct Parser<'s> {
+    reader: Reader<'s>,
+    openers: Vec<QName<'s>>,
+    pending: Option<Event<'s>>,
+}
+
+impl<'s> Parser<'s> {
+    #[inline]
+    pub fn new(text: &'s str) -> Parser<'s> {
+        Parser {
+            reader: Reader::new(text),
+            openers: Vec::new(),
+            pending: None,
+        }
+    }
+
+    fn read_attributes(reader: &mut Reader<'s>) -> Vec<Attribute<'s>> {
+        let mut attributes = Vec::new();
+
+        loop {
+            reader.consume_whitespace();
+
+            match reader.read_qname() {
+                Some(name) => {
+                    reader.consume_whitespace();
+                    reader.read_char('=');
+                    reader.consume_whitespace();
+
+                    match reader.peek() {
+                        Some(quote) if quote == '"' || quote == '\'' => {
+                            reader.read_char(quote);
+                            let value = reader.capture(|r| r.consume_until_char(quote)).unwrap_or("");
+                            reader.read_char(quote);
+
+                            attributes.push(Attribute { name: name, value: value });
+                        },
+                        _ => break,
+                    }
+                },
+                None => break,
+            }
+        }
+
+        attributes
+    }
+
+    /// Consumes an unrecognized `<!...>` declaration such as `<!DOCTYPE svg>`,
+    /// honoring a bracketed internal subset (`<!DOCTYPE svg [ ... ]>`) so a
+    /// `>` nested inside it doesn't end the declaration early. Returns the
+    /// content between `<!` and the closing `>`, or `None` if the input
+    /// ends before the declaration is closed.
+    fn read_declaration(reader: &mut Reader<'s>) -> Option<&'s str> {
+        let depth = Cell::new(0usize);
+        let closed = Cell::new(false);
+
+        let captured = reader.capture(|r| {
+            loop {
+                match r.peek() {
+                    Some('[') => {
+                        depth.set(depth.get() + 1);
+                        r.next();
+                    },
+                    Some(']') if depth.get() > 0 => {
+                        depth.set(depth.get() - 1);
+                        r.next();
+                    },
+                    Some('>') if depth.get() == 0 => {
+                        r.next();
+                        closed.set(true);
+                        break;
+                    },
+                    Some(_) => {
+                        r.next();
+                    },
+                    None => break,
+                }
+            }
+        });
+
+        if !closed.get() {
+            return None;
+        }
+
+        captured.map(|text| &text[..text.len() - 1])
+    }
+
+    /// Consumes everything up to and including the given literal `needle`,
+    /// returning the text in between. Returns `None` if the input ends
+    /// before `needle` is found, rather than returning a truncated guess
+    /// at the content — the caller (and the input) is unterminated.
+    fn read_until(reader: &mut Reader<'s>, needle: &str) -> Option<&'s str> {
+        let found = Cell::new(false);
+
+        let captured = reader.capture(|r| {
+            loop {
+                if r.peek_str(needle) {
+                    for _ in needle.chars() {
+                        r.next();
+                    }
+                    found.set(true);
+                    break;
+                }
+
+                if r.next().is_none() {
+                    break;
+                }
+            }
+        });
+
+        if !found.get() {
+            return None;
+        }
+
+        captured.map(|text| &text[..text.len() - needle.len()])
+    }
+}
+
+impl<'s> Iterator for Parser<'s> {
+    type Item = Event<'s>;
+
+    fn next(&mut self) -> Option<Event<'s>> {
+        if let Some(event) = self.pending.take() {
+            return Some(event);
+        }
+
+        if let Some(text) = self.reader.capture(|r| r.consume_while(|c| c != '<')) {
+            return Some(Event::Text(text));
+        }
+
+        match self.reader.peek() {
+            Some('<') => {},
+            _ => return None,
+        }
+        self.reader.next();
+
+        match self.reader.peek() {
+            Some('/') => {
+                self.reader.next();
+                let name = self.reader.read_qname();
+                self.reader.consume_whitespace();
+                self.reader.read_char('>');
+
+                name.map(|name| {
+                    match self.openers.pop() {
+                        Some(opener) if opener == name => Event::Close { name: name },
+                        expected => Event::UnmatchedClose { expected: expected, found: name },
+                    }
+                })
+            },
+            Some('!') => {
+                self.reader.next();
+
+                if self.reader.peek_str("--") {
+                    self.reader.read_char('-');
+                    self.reader.read_char('-');
+                    Parser::read_until(&mut self.reader, "-->").map(Event::Comment)
+                } else if self.reader.peek_str("[CDATA[") {
+                    self.reader.read_char('[');
+                    for c in "CDATA[".chars() {
+                        self.reader.read_char(c);
+                    }
+                    Parser::read_until(&mut self.reader, "]]>").map(Event::CData)
+                } else {
+                    Parser::read_declaration(&mut self.reader).map(Event::Doctype)
+                }
+            },
+            Some('?') => {
+                self.reader.next();
+                Parser::read_until(&mut self.reader, "?>").map(|_| Event::Declaration)
+            },
+            _ => {
+                self.reader.read_qname().map(|name| {
+                    let attributes = Parser::read_attributes(&mut self.reader);
+                    self.reader.consume_whitespace();
+                    let self_closing = self.reader.read_char('/').is_some();
+                    self.reader.consume_whitespace();
+                    self.reader.read_char('>');
+
+                    if self_closing {
+                        self.pending = Some(Event::Close { name: name });
+                    } else {
+                        self.openers.push(name);
+                    }
+
+                    Event::Open { name: name, attributes: attributes }
+                })
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Attribute, Event, Parser};
+    use reader::QName;
+
+    /// Builds an unprefixed `QName` for the common case in these tests.
+    fn q(local: &str) -> QName {
+        QName { prefix: None, local: local }
+    }
+
+    #[test]
+    fn open_and_close() {
+        let mut parser = Parser::new("<svg><rect/></svg>");
+
+        assert_eq!(parser.next(), Some(Event::Open { name: q("svg"), attributes: vec![] }));
+        assert_eq!(parser.next(), Some(Event::Open { name: q("rect"), attributes: vec![] }));
+        assert_eq!(parser.next(), Some(Event::Close { name: q("rect") }));
+        assert_eq!(parser.next(), Some(Event::Close { name: q("svg") }));
+        assert_eq!(parser.next(), None);
+    }
+
+    #[test]
+    fn unmatched_close_is_reported() {
+        let mut parser = Parser::new("<svg><rect></svg>");
+
+        assert_eq!(parser.next(), Some(Event::Open { name: q("svg"), attributes: vec![] }));
+        assert_eq!(parser.next(), Some(Event::Open { name: q("rect"), attributes: vec![] }));
+        assert_eq!(parser.next(), Some(Event::UnmatchedClose { expected: Some(q("rect")), found: q("svg") }));
+    }
+
+    #[test]
+    fn attributes() {
+        let mut parser = Parser::new("<rect width=\"10\" height='20'/>");
+
+        assert_eq!(parser.next(), Some(Event::Open {
+            name: q("rect"),
+            attributes: vec![
+                Attribute { name: q("width"), value: "10" },
+                Attribute { name: q("height"), value: "20" },
+            ],
+        }));
+        assert_eq!(parser.next(), Some(Event::Close { name: q("rect") }));
+    }
+
+    #[test]
+    fn namespace_prefixed_names_are_split_into_prefix_and_local() {
+        let mut parser = Parser::new("<svg:use xlink:href=\"#a\"/>");
+
+        assert_eq!(parser.next(), Some(Event::Open {
+            name: QName { prefix: Some("svg"), local: "use" },
+            attributes: vec![
+                Attribute { name: QName { prefix: Some("xlink"), local: "href" }, value: "#a" },
+            ],
+        }));
+        assert_eq!(parser.next(), Some(Event::Close { name: QName { prefix: Some("svg"), local: "use" } }));
+    }
+
+    #[test]
+    fn text() {
+        let mut parser = Parser::new("<title>Shapes</title>");
+
+        assert_eq!(parser.next(), Some(Event::Open { name: q("title"), attributes: vec![] }));
+        assert_eq!(parser.next(), Some(Event::Text("Shapes")));
+        assert_eq!(parser.next(), Some(Event::Close { name: q("title") }));
+    }
+
+    #[test]
+    fn unterminated_comment_does_not_truncate_silently() {
+        let mut parser = Parser::new("<!-- never closes");
+
+        assert_eq!(parser.next(), None);
+    }
+
+    #[test]
+    fn comment_and_cdata() {
+        let mut parser = Parser::new("<!-- hi --><![CDATA[<raw>]]>");
+
+        assert_eq!(parser.next(), Some(Event::Comment(" hi ")));
+        assert_eq!(parser.next(), Some(Event::CData("<raw>")));
+        assert_eq!(parser.next(), None);
+    }
+
+    #[test]
+    fn doctype_is_not_mistaken_for_cdata() {
+        let mut parser = Parser::new("<!DOCTYPE svg><svg/>");
+
+        assert_eq!(parser.next(), Some(Event::Doctype("DOCTYPE svg")));
+        assert_eq!(parser.next(), Some(Event::Open { name: q("svg"), attributes: vec![] }));
+        assert_eq!(parser.next(), Some(Event::Close { name: q("svg") }));
+        assert_eq!(parser.next(), None);
+    }
+
+    #[test]
+    fn doctype_with_internal_subset_is_not_split_on_nested_close() {
+        let mut parser = Parser::new("<!DOCTYPE svg [ <!ENTITY x \"y\"> ]><svg/>");
+
+        assert_eq!(parser.next(), Some(Event::Doctype("DOCTYPE svg [ <!ENTITY x \"y\"> ]")));
+        assert_eq!(parser.next(), Some(Event::Open { name: q("svg"), attributes: vec![] }));
+    }
+
+    #[test]
+    fn unterminated_doctype_does_not_truncate_silently() {
+        let mut parser = Parser::new("<!DOCTYPE svg");
+
+        assert_eq!(parser.next(), None);
+    }
+
+    #[test]
+    fn declaration() {
+        let mut parser = Parser::new("<?xml version=\"1.0\"?><svg/>");
+
+        assert_eq!(parser.next(), Some(Event::Declaration));
+        assert_eq!(parser.next(), Some(Event::Open { name: q("svg"), attributes: vec![] }));
+        assert_eq!(parser.next(), Some(Event::Close { name: q("svg") }));
+    }
+}
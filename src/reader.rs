@@ -1,6 +1,9 @@
+use std::fmt;
 use std::iter::Peekable;
 use std::str::Chars;
 
+use diagnostics::{self, Span};
+
 pub struct Reader<'s> {
     text: &'s str,
 
@@ -11,6 +14,32 @@ pub struct Reader<'s> {
     cursor: Peekable<Chars<'s>>,
 }
 
+/// A saved cursor position, taken with `Reader::mark` and restored with
+/// `Reader::reset`, for speculative parsing that may need to backtrack.
+pub struct Mark<'s> {
+    line: usize,
+    column: usize,
+    offset: usize,
+    cursor: Peekable<Chars<'s>>,
+}
+
+/// A qualified name split into an optional namespace `prefix` and the
+/// `local` part, e.g. `xlink:href` -> `prefix: Some("xlink"), local: "href"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QName<'s> {
+    pub prefix: Option<&'s str>,
+    pub local: &'s str,
+}
+
+impl<'s> fmt::Display for QName<'s> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.prefix {
+            Some(prefix) => write!(f, "{}:{}", prefix, self.local),
+            None => write!(f, "{}", self.local),
+        }
+    }
+}
+
 impl<'s> Reader<'s> {
     #[inline]
     pub fn new(text: &'s str) -> Reader<'s> {
@@ -25,7 +54,7 @@ impl<'s> Reader<'s> {
         }
     }
 
-    pub fn capture<F>(&mut self, block: F) -> Option<&str> where F: Fn(&mut Reader<'s>) {
+    pub fn capture<F>(&mut self, block: F) -> Option<&'s str> where F: Fn(&mut Reader<'s>) {
         let start = self.offset;
         block(self);
         let end = self.offset;
@@ -37,6 +66,64 @@ impl<'s> Reader<'s> {
         }
     }
 
+    /// Like `capture`, but returns the captured range as a `Span` rather
+    /// than a borrowed slice, for callers that want to hold on to a
+    /// position (e.g. to render a diagnostic) without borrowing `self`.
+    pub fn capture_span<F>(&mut self, block: F) -> Option<Span> where F: Fn(&mut Reader<'s>) {
+        let start = self.offset;
+        block(self);
+        let end = self.offset;
+
+        if end > start {
+            Some(Span { start_offset: start, end_offset: end })
+        } else {
+            None
+        }
+    }
+
+    /// Renders a compiler-quality snippet pinpointing `span` within this
+    /// reader's source text, underlining it with `message` attached.
+    pub fn render_snippet(&self, span: Span, message: &str) -> String {
+        diagnostics::render_snippet(self.text, span, message)
+    }
+
+    /// Checks whether the upcoming input starts with `prefix`, without
+    /// consuming anything. Lets a parser recognize multi-character
+    /// constructs such as `<!--` or `<![CDATA[` before committing to them.
+    pub fn peek_str(&mut self, prefix: &str) -> bool {
+        let mut cursor = self.cursor.clone();
+
+        for expected in prefix.chars() {
+            match cursor.next() {
+                Some(c) if c == expected => {},
+                _ => return false,
+            }
+        }
+
+        true
+    }
+
+    /// Captures the current cursor position so it can later be restored
+    /// with `reset`, enabling backtracking after a failed speculative parse.
+    #[inline]
+    pub fn mark(&self) -> Mark<'s> {
+        Mark {
+            line: self.line,
+            column: self.column,
+            offset: self.offset,
+            cursor: self.cursor.clone(),
+        }
+    }
+
+    /// Rewinds this reader to a position previously captured with `mark`.
+    #[inline]
+    pub fn reset(&mut self, mark: Mark<'s>) {
+        self.line = mark.line;
+        self.column = mark.column;
+        self.offset = mark.offset;
+        self.cursor = mark.cursor;
+    }
+
     pub fn consume_while<F>(&mut self, check: F) where F: Fn(char) -> bool {
         loop {
             match self.peek() {
@@ -143,7 +230,7 @@ impl<'s> Reader<'s> {
     }
 
     /// http://www.w3.org/TR/REC-xml/#NT-Name
-    pub fn read_name(&mut self) -> Option<&str> {
+    pub fn read_name(&mut self) -> Option<&'s str> {
         self.capture(|reader| {
             match reader.read_name_start_char() {
                 Some(_) => {
@@ -158,6 +245,103 @@ impl<'s> Reader<'s> {
             }
         })
     }
+
+    /// Like `read_name_start_char`, but per the Namespaces in XML `NCName`
+    /// production: excludes `:`, which separates a namespace prefix from
+    /// the local part.
+    pub fn read_ncname_start_char(&mut self) -> Option<char> {
+        match self.peek() {
+            Some(c) => match c {
+                'A'...'Z' |
+                '_' |
+                'a'...'z' |
+                '\u{C0}'...'\u{D6}' |
+                '\u{D8}'...'\u{F6}' |
+                '\u{F8}'...'\u{2FF}' |
+                '\u{370}'...'\u{37D}' |
+                '\u{37F}'...'\u{1FFF}' |
+                '\u{200C}'...'\u{200D}' |
+                '\u{2070}'...'\u{218F}' |
+                '\u{2C00}'...'\u{2FEF}' |
+                '\u{3001}'...'\u{D7FF}' |
+                '\u{F900}'...'\u{FDCF}' |
+                '\u{FDF0}'...'\u{FFFD}' |
+                '\u{10000}'...'\u{EFFFF}' => {
+                    self.next();
+                    Some(c)
+                },
+                _ => None,
+            },
+            _ => None
+        }
+    }
+
+    /// Like `read_name_char`, but excludes `:` (see `read_ncname_start_char`).
+    pub fn read_ncname_char(&mut self) -> Option<char> {
+        self.read_ncname_start_char().or_else(|| {
+            match self.peek() {
+                Some(c) => match c {
+                    '-' |
+                    '.' |
+                    '0'...'9' |
+                    '\u{B7}' |
+                    '\u{0300}'...'\u{036F}' |
+                    '\u{203F}'...'\u{2040}' => {
+                        self.next();
+                        Some(c)
+                    },
+                    _ => None,
+                },
+                _ => None,
+            }
+        })
+    }
+
+    /// http://www.w3.org/TR/REC-xml-names/#NT-NCName
+    pub fn read_ncname(&mut self) -> Option<&'s str> {
+        self.capture(|reader| {
+            match reader.read_ncname_start_char() {
+                Some(_) => {
+                    loop {
+                        match reader.read_ncname_char() {
+                            Some(_) => {},
+                            _ => break,
+                        }
+                    }
+                },
+                _ => {},
+            }
+        })
+    }
+
+    /// http://www.w3.org/TR/REC-xml-names/#NT-QName
+    ///
+    /// Reads an `NCName`, and if it's immediately followed by `:`, a
+    /// second `NCName` as the local part. Malformed input (a trailing
+    /// colon, or a colon not followed by a valid `NCName`) rewinds the
+    /// reader and returns `None` rather than consuming a partial name.
+    pub fn read_qname(&mut self) -> Option<QName<'s>> {
+        let mark = self.mark();
+
+        let first = match self.read_ncname() {
+            Some(name) => name,
+            None => return None,
+        };
+
+        if self.peek() == Some(':') {
+            self.next();
+
+            match self.read_ncname() {
+                Some(local) => Some(QName { prefix: Some(first), local: local }),
+                None => {
+                    self.reset(mark);
+                    None
+                },
+            }
+        } else {
+            Some(QName { prefix: None, local: first })
+        }
+    }
 }
 
 impl<'s> Iterator for Reader<'s> {
@@ -172,7 +356,7 @@ impl<'s> Iterator for Reader<'s> {
                 } else {
                     self.column += 1;
                 }
-                self.offset += 1;
+                self.offset += c.len_utf8();
                 Some(c)
             }
             _ => None,
@@ -183,6 +367,108 @@ impl<'s> Iterator for Reader<'s> {
 #[cfg(test)]
 mod tests {
     use super::Reader;
+    use diagnostics::Span;
+
+    #[test]
+    fn capture_span() {
+        let mut reader = Reader::new("abcdefg");
+
+        reader.consume_any("ab");
+        let span = reader.capture_span(|reader| {
+            reader.consume_any("cde");
+        });
+
+        assert_eq!(span, Some(Span { start_offset: 2, end_offset: 5 }));
+    }
+
+    #[test]
+    fn capture_counts_bytes_not_chars() {
+        let mut reader = Reader::new("<svg>中<rect/>");
+
+        reader.consume_until_char('中');
+        let tag = reader.capture(|reader| {
+            reader.read_char('中');
+            reader.consume_until_char('<');
+        });
+
+        assert_eq!(tag.unwrap(), "中");
+        assert_eq!(reader.position(), (1, 7));
+    }
+
+    #[test]
+    fn render_snippet() {
+        let reader = Reader::new("<svg wdith=\"10\">");
+
+        let rendered = reader.render_snippet(Span { start_offset: 5, end_offset: 10 }, "unknown attribute");
+        assert_eq!(rendered, "1 | <svg wdith=\"10\">\n  |      ^^^^^ unknown attribute");
+    }
+
+    #[test]
+    fn peek_str() {
+        let mut reader = Reader::new("<!--comment-->");
+
+        reader.consume_any("<!");
+        assert!(reader.peek_str("--"));
+        assert!(!reader.peek_str("[CDATA["));
+
+        assert_eq!(reader.position(), (1, 3));
+    }
+
+    #[test]
+    fn mark_and_reset() {
+        let mut reader = Reader::new("<!--comment-->");
+
+        reader.consume_any("<!");
+        let mark = reader.mark();
+
+        reader.consume_any("--comment--");
+        assert_eq!(reader.position(), (1, 14));
+
+        reader.reset(mark);
+        assert_eq!(reader.position(), (1, 3));
+        assert!(reader.peek_str("--"));
+    }
+
+    #[test]
+    fn read_ncname() {
+        let mut reader = Reader::new("xlink:href");
+        assert_eq!(reader.read_ncname().unwrap(), "xlink");
+    }
+
+    #[test]
+    fn read_qname_with_prefix() {
+        let mut reader = Reader::new("xlink:href=\"\"");
+
+        let qname = reader.read_qname().unwrap();
+        assert_eq!(qname.prefix, Some("xlink"));
+        assert_eq!(qname.local, "href");
+        assert_eq!(reader.position(), (1, 11));
+    }
+
+    #[test]
+    fn read_qname_without_prefix() {
+        let mut reader = Reader::new("width=\"10\"");
+
+        let qname = reader.read_qname().unwrap();
+        assert_eq!(qname.prefix, None);
+        assert_eq!(qname.local, "width");
+    }
+
+    #[test]
+    fn read_qname_rewinds_on_trailing_colon() {
+        let mut reader = Reader::new("foo:");
+
+        assert!(reader.read_qname().is_none());
+        assert_eq!(reader.position(), (1, 1));
+    }
+
+    #[test]
+    fn read_qname_rewinds_on_second_colon() {
+        let mut reader = Reader::new("foo::bar");
+
+        assert!(reader.read_qname().is_none());
+        assert_eq!(reader.position(), (1, 1));
+    }
 
     #[test]
     fn capture() {